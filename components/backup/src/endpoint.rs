@@ -14,13 +14,19 @@ use tikv::raftstore::coprocessor::RegionInfoAccessor;
 use tikv::raftstore::store::util::find_peer;
 use tikv::server::transport::ServerRaftStoreRouter;
 use tikv::storage::kv::{
-    Engine, Error as EngineError, RegionInfoProvider, ScanMode, StatisticsSummary,
+    Engine, Error as EngineError, RegionInfoProvider, ScanMode, Snapshot, StatisticsSummary,
+};
+use tikv::storage::txn::{
+    EntryBatch, Error as TxnError, Msg, Scanner, ScannerBuilder, SnapshotStore, Store, TxnEntry,
 };
-use tikv::storage::txn::{EntryBatch, Error as TxnError, Msg, Scanner, SnapshotStore, Store};
 use tikv::storage::{Key, Statistics};
 use tikv_util::worker::{Runnable, RunnableWithTimer};
 use tokio_threadpool::ThreadPool;
 
+use crate::checkpoint;
+use crate::config::BackupConfig;
+use crate::limiter::Limiter;
+use crate::merkle::{self, MerkleTree};
 use crate::*;
 
 pub struct Task {
@@ -70,26 +76,54 @@ pub struct BackupRange {
     leader: Peer,
 }
 
+/// The in-progress checkpoint manifest plus a counter of completions not
+/// yet flushed to `Storage`, so the manifest can be persisted in batches
+/// instead of on every single completed range (see `manifest_flush_interval`).
+#[derive(Default)]
+struct ManifestState {
+    manifest: checkpoint::Manifest,
+    dirty: usize,
+}
+
 pub struct Endpoint<E: Engine, R: RegionInfoProvider> {
     store_id: u64,
     engine: E,
     region_info: R,
     workers: ThreadPool,
     db: Arc<DB>,
+    config: BackupConfig,
+    limiter: Limiter,
 }
 
 impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
-    pub fn new(store_id: u64, engine: E, region_info: R, db: Arc<DB>) -> Endpoint<E, R> {
+    pub fn new(
+        store_id: u64,
+        engine: E,
+        region_info: R,
+        db: Arc<DB>,
+        config: BackupConfig,
+    ) -> Endpoint<E, R> {
+        let workers = tokio_threadpool::Builder::new()
+            .pool_size(config.num_threads)
+            .build();
+        let limiter = Limiter::new(config.rate_limit_bytes_per_sec);
         Endpoint {
             store_id,
             engine,
             region_info,
-            // TODO: support more config.
-            workers: ThreadPool::new(),
+            workers,
             db,
+            config,
+            limiter,
         }
     }
 
+    /// A handle to the shared rate limiter so it can be retuned at
+    /// runtime without restarting the backup worker.
+    pub fn limiter(&self) -> Limiter {
+        self.limiter.clone()
+    }
+
     fn seek_backup_range(
         &self,
         start_key: Option<Key>,
@@ -156,68 +190,104 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
         rx
     }
 
+    /// Maximum number of attempts (the first one included) made for a
+    /// range that keeps hitting transient scan/write failures (e.g. a
+    /// leader change or a node restart) before it is surfaced as a
+    /// permanent failure in the `BackupResponse`.
+    const MAX_RANGE_ATTEMPTS: u32 = 3;
+
     fn dispatch_backup_range(
         &self,
         brange: BackupRange,
         start_ts: u64,
         end_ts: u64,
         storage: Arc<dyn Storage>,
+        manifest: Arc<Mutex<ManifestState>>,
         tx: mpsc::Sender<(BackupRange, Result<(Vec<File>, Statistics)>)>,
     ) {
-        // TODO: support incremental backup
-        let _ = start_ts;
-
-        let backup_ts = end_ts;
-        let mut ctx = Context::new();
-        ctx.set_region_id(brange.region.get_id());
-        ctx.set_region_epoch(brange.region.get_region_epoch().to_owned());
-        ctx.set_peer(brange.leader.clone());
-        // TODO: make it async.
-        let snapshot = self.engine.snapshot(&ctx).unwrap();
+        let engine = self.engine.clone();
         let db = self.db.clone();
         let store_id = self.store_id;
+        let limiter = self.limiter.clone();
+        let region = brange.region.clone();
+        let leader = brange.leader.clone();
+        let epoch_version = region.get_region_epoch().get_version();
+        let flush_interval = self.config.manifest_flush_interval.max(1);
         self.workers.spawn(lazy(move || {
-            let snap_store = SnapshotStore::new(
-                snapshot,
-                backup_ts,
-                IsolationLevel::SI,
-                false, /* fill_cache */
-            );
-            let start_key = brange.start_key.clone();
-            let end_key = brange.end_key.clone();
-            let mut scanner = snap_store
-                .entry_scanner(start_key.clone(), end_key.clone())
-                .unwrap();
-            let mut batch = EntryBatch::with_capacity(1024);
-            let name = backup_file_name(store_id, &brange.region);
-            let mut writer = match BackupWriter::new(db, &name) {
-                Ok(w) => w,
-                Err(e) => {
-                    return tx.send((brange, Err(e))).map_err(|_| ());
-                }
-            };
+            let mut attempt = 0;
             loop {
-                if let Err(e) = scanner.scan_entries(&mut batch) {
-                    return tx.send((brange, Err(e.into()))).map_err(|_| ());
-                };
-                if batch.len() == 0 {
-                    break;
-                }
-                debug!("backup scan entries"; "len" => batch.len());
-                // Build sst files.
-                if let Err(e) = writer.write(batch.drain()) {
-                    return tx.send((brange, Err(e))).map_err(|_| ());
+                attempt += 1;
+                let mut ctx = Context::new();
+                ctx.set_region_id(region.get_id());
+                ctx.set_region_epoch(region.get_region_epoch().to_owned());
+                ctx.set_peer(leader.clone());
+                let res = engine.snapshot(&ctx).map_err(Error::from).and_then(|snapshot| {
+                    scan_and_write_range(
+                        &brange,
+                        start_ts,
+                        end_ts,
+                        epoch_version,
+                        snapshot,
+                        db.clone(),
+                        store_id,
+                        &region,
+                        &storage,
+                        &limiter,
+                    )
+                });
+                match res {
+                    Ok((files, stat)) => {
+                        let start_key = brange
+                            .start_key
+                            .as_ref()
+                            .map_or_else(Vec::new, |k| k.as_encoded().clone());
+                        let end_key = brange
+                            .end_key
+                            .as_ref()
+                            .map_or_else(Vec::new, |k| k.as_encoded().clone());
+                        let entry = checkpoint::CheckpointEntry {
+                            region_id: region.get_id(),
+                            epoch_version,
+                            start_key,
+                            end_key,
+                            end_ts,
+                            files: checkpoint::files_to_tuples(&files),
+                        };
+                        let snapshot = {
+                            let mut state = manifest.lock().unwrap();
+                            state.manifest.record(entry);
+                            state.dirty += 1;
+                            if state.dirty >= flush_interval {
+                                state.dirty = 0;
+                                Some(checkpoint::encode(&state.manifest))
+                            } else {
+                                None
+                            }
+                        };
+                        // PUT the encoded snapshot outside the lock: the
+                        // manifest grows without bound over the task, so
+                        // holding the mutex across a network round trip
+                        // would serialize every other in-flight range
+                        // behind it.
+                        if let Some(data) = snapshot {
+                            if let Err(e) = storage.put(checkpoint::MANIFEST_NAME, &data) {
+                                warn!("backup failed to persist checkpoint manifest"; "error" => ?e);
+                            }
+                        }
+                        return tx.send((brange, Ok((files, stat)))).map_err(|_| ());
+                    }
+                    Err(e) => {
+                        if attempt < Self::MAX_RANGE_ATTEMPTS && e.is_retryable() {
+                            warn!("backup range failed, retrying";
+                                "region_id" => region.get_id(),
+                                "attempt" => attempt,
+                                "error" => ?e);
+                            continue;
+                        }
+                        return tx.send((brange, Err(e))).map_err(|_| ());
+                    }
                 }
             }
-            // Save sst files to storage.
-            let files = match writer.save(&storage) {
-                Ok(files) => files,
-                Err(e) => {
-                    return tx.send((brange, Err(e))).map_err(|_| ());
-                }
-            };
-            let stat = scanner.take_statistics();
-            tx.send((brange, Ok((files, stat)))).map_err(|_| ())
         }));
     }
 
@@ -235,56 +305,135 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
         };
         let rx = self.seek_backup_range(start_key, end_key);
 
+        // Load the checkpoint manifest for this destination, if this task
+        // is resuming a backup that was interrupted partway. Ranges it
+        // already lists are skipped below instead of being re-scanned.
+        let manifest = task
+            .storage
+            .get(checkpoint::MANIFEST_NAME)
+            .ok()
+            .and_then(|o| o)
+            .and_then(|data| checkpoint::decode(&data))
+            .unwrap_or_default();
+        let manifest = Arc::new(Mutex::new(ManifestState {
+            manifest,
+            dirty: 0,
+        }));
+
         // TODO: should we combine seek_backup_range and dispatch_backup_range?
         let (res_tx, res_rx) = mpsc::channel();
-        for brange in rx {
-            let tx = res_tx.clone();
-            self.dispatch_backup_range(brange, task.end_ts, task.end_ts, task.storage.clone(), tx);
-        }
-
-        // Drop the extra sender so that for loop does not hang up.
-        drop(res_tx);
         let mut summary = Statistics::default();
+        let task_start_ts = task.start_ts;
+        let task_end_ts = task.end_ts;
         let resp = task.resp;
-        for (brange, res) in res_rx {
+        let mut handle_result =
+            |brange: BackupRange, res: Result<(Vec<File>, Statistics)>, summary: &mut Statistics| {
+                let start_key = brange
+                    .start_key
+                    .map_or_else(|| vec![], |k| k.into_raw().unwrap());
+                let end_key = brange
+                    .end_key
+                    .map_or_else(|| vec![], |k| k.into_raw().unwrap());
+                let mut response = BackupResponse::new();
+                response.set_start_key(start_key.clone());
+                response.set_end_key(end_key.clone());
+                match res {
+                    Ok((mut files, stat)) => {
+                        info!("backup region finish";
+                            "region" => ?brange.region,
+                            "start_key" => ?start_key,
+                            "end_key" => ?end_key,
+                            "details" => ?stat);
+                        summary.add(&stat);
+                        // Fill key range and ts.
+                        for file in files.iter_mut() {
+                            file.set_start_key(start_key.clone());
+                            file.set_end_key(end_key.clone());
+                            file.set_start_version(task_start_ts);
+                            file.set_end_version(task_end_ts);
+                        }
+                        response.set_files(files.into());
+                        resp.unbounded_send(Some(response)).unwrap();
+                    }
+                    Err(e) => {
+                        error!("backup region failed";
+                            "region" => ?brange.region,
+                            "start_key" => ?response.get_start_key(),
+                            "end_key" => ?response.get_end_key(),
+                            "error" => ?e);
+                        response.set_error(e.into());
+                        resp.unbounded_send(Some(response)).unwrap();
+                    }
+                }
+            };
+
+        // Keep at most `batch_size` regions in flight at once instead of
+        // draining the whole `seek_backup_range` receiver up front, so
+        // memory stays flat on stores with tens of thousands of regions.
+        let batch_size = self.config.batch_size.max(1);
+        let mut in_flight = 0usize;
+        for brange in rx {
+            let region_id = brange.region.get_id();
+            let epoch_version = brange.region.get_region_epoch().get_version();
             let start_key = brange
                 .start_key
-                .map_or_else(|| vec![], |k| k.into_raw().unwrap());
+                .as_ref()
+                .map_or_else(Vec::new, |k| k.as_encoded().clone());
             let end_key = brange
                 .end_key
-                .map_or_else(|| vec![], |k| k.into_raw().unwrap());
-            let mut response = BackupResponse::new();
-            response.set_start_key(start_key.clone());
-            response.set_end_key(end_key.clone());
-            match res {
-                Ok((mut files, stat)) => {
-                    info!("backup region finish";
-                        "region" => ?brange.region,
-                        "start_key" => ?start_key,
-                        "end_key" => ?end_key,
-                        "details" => ?stat);
-                    summary.add(&stat);
-                    // Fill key range and ts.
-                    for file in files.iter_mut() {
-                        file.set_start_key(start_key.clone());
-                        file.set_end_key(end_key.clone());
-                        file.set_start_version(task.start_ts);
-                        file.set_end_version(task.end_ts);
-                    }
-                    response.set_files(files.into());
-                    resp.unbounded_send(Some(response)).unwrap();
-                }
-                Err(e) => {
-                    error!("backup region failed";
-                        "region" => ?brange.region,
-                        "start_key" => ?response.get_start_key(),
-                        "end_key" => ?response.get_end_key(),
-                        "error" => ?e);
-                    response.set_error(e.into());
-                    resp.unbounded_send(Some(response)).unwrap();
-                }
+                .as_ref()
+                .map_or_else(Vec::new, |k| k.as_encoded().clone());
+            let cached = manifest
+                .lock()
+                .unwrap()
+                .manifest
+                .find(region_id, epoch_version, &start_key, &end_key, task.end_ts)
+                .cloned();
+            if let Some(entry) = cached {
+                // Already covered by a prior, interrupted run of this same
+                // destination: skip the scan entirely and reuse the files
+                // it already produced.
+                let files = checkpoint::entry_to_files(&entry);
+                handle_result(
+                    brange,
+                    Ok((files, Statistics::default())),
+                    &mut summary,
+                );
+                continue;
+            }
+
+            let tx = res_tx.clone();
+            self.dispatch_backup_range(
+                brange,
+                task.start_ts,
+                task.end_ts,
+                task.storage.clone(),
+                manifest.clone(),
+                tx,
+            );
+            in_flight += 1;
+            if in_flight >= batch_size {
+                let (brange, res) = res_rx.recv().unwrap();
+                handle_result(brange, res, &mut summary);
+                in_flight -= 1;
             }
         }
+
+        // Drop the extra sender so that the final drain does not hang up.
+        drop(res_tx);
+        for (brange, res) in res_rx {
+            handle_result(brange, res, &mut summary);
+        }
+
+        // Always flush the final state, even if the last batch of
+        // completions never crossed `manifest_flush_interval`: otherwise a
+        // resume after this run would have to rescan whatever progress
+        // since the last throttled flush was never persisted.
+        let final_manifest = checkpoint::encode(&manifest.lock().unwrap().manifest);
+        if let Err(e) = task.storage.put(checkpoint::MANIFEST_NAME, &final_manifest) {
+            warn!("backup failed to persist final checkpoint manifest"; "error" => ?e);
+        }
+
         info!("backup finished";
             "take" => ?start.elapsed(),
             "summary" => ?summary);
@@ -295,14 +444,153 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
 impl<E: Engine, R: RegionInfoProvider> Runnable<Task> for Endpoint<E, R> {
     fn run(&mut self, task: Task) {
         info!("run backup task"; "task" => %task);
-        if task.start_ts == task.end_ts {
-            self.handle_backup_task(task);
-        } else {
-            // TODO: support incremental backup
-            error!("incremental backup is not supported yet");
-            task.resp.unbounded_send(None).unwrap();
+        // `start_ts == end_ts` is a full backup; `start_ts < end_ts` is an
+        // incremental backup that reuses the Merkle fingerprints recorded
+        // by the previous run ending at `start_ts`, if any are cached.
+        self.handle_backup_task(task);
+    }
+}
+
+/// Name of the auxiliary blob a region's Merkle fingerprint tree is
+/// persisted under. Deliberately independent of the region epoch so it
+/// can be looked up and validated against the *current* epoch on the next
+/// run.
+fn merkle_tree_name(store_id: u64, region_id: u64) -> String {
+    format!("{}_{}.merkle", store_id, region_id)
+}
+
+/// Scan one `BackupRange` at `backup_ts = end_ts`, restrict the output to
+/// the Merkle buckets that changed since `start_ts`, and ship the result
+/// to `storage`. Pulled out of `dispatch_backup_range` so a transient
+/// failure can retry the whole scan+write against a freshly taken
+/// snapshot.
+fn scan_and_write_range<S: Snapshot>(
+    brange: &BackupRange,
+    start_ts: u64,
+    end_ts: u64,
+    epoch_version: u64,
+    snapshot: S,
+    db: Arc<DB>,
+    store_id: u64,
+    region: &Region,
+    storage: &Arc<dyn Storage>,
+    limiter: &Limiter,
+) -> Result<(Vec<File>, Statistics)> {
+    let start_key = brange.start_key.clone();
+    let end_key = brange.end_key.clone();
+    let name = backup_file_name(store_id, region);
+    let tree_name = merkle_tree_name(store_id, region.get_id());
+
+    // Load the fingerprint tree computed on the previous backup of this
+    // region, if any. A region epoch mismatch means the region
+    // split/merged since, so the cached tree no longer lines up with this
+    // run's bucket layout and must be dropped.
+    let prior_tree = storage
+        .get(&tree_name)
+        .ok()
+        .and_then(|o| o)
+        .and_then(|data| merkle::decode(&data))
+        .filter(|t| t.epoch_version == epoch_version);
+    // `start_ts == end_ts` is a full backup even when a stale tree happens
+    // to be cached for this destination; only `start_ts < end_ts` asks for
+    // the incremental, changed-buckets-only behavior.
+    let incremental = prior_tree.is_some() && start_ts < end_ts;
+
+    let snap_store = SnapshotStore::new(
+        snapshot,
+        end_ts,
+        IsolationLevel::SI,
+        false, /* fill_cache */
+    );
+    let mut scanner = if incremental {
+        // Hint the iterator to skip whole SST blocks whose newest commit
+        // is at or before `start_ts`, the same `hint_min_ts` mechanism the
+        // GC compaction filter uses, so unchanged older versions are never
+        // re-read from disk.
+        ScannerBuilder::new(snap_store, end_ts, false)
+            .range(start_key.clone(), end_key.clone())
+            .hint_min_ts(Some(start_ts))
+            .build_entry_scanner(start_ts, false)
+            .map_err(Error::from)?
+    } else {
+        snap_store
+            .entry_scanner(start_key.clone(), end_key.clone())
+            .map_err(Error::from)?
+    };
+
+    let start_raw = start_key
+        .as_ref()
+        .map_or_else(Vec::new, |k| k.as_encoded().clone());
+    let end_raw = end_key.as_ref().map(|k| k.as_encoded().clone());
+    let mut new_tree = MerkleTree::empty(epoch_version, &start_raw, end_raw.as_deref());
+    if let Some(prior) = &prior_tree {
+        new_tree.leaves = prior.leaves.clone();
+    }
+
+    let mut batch = EntryBatch::with_capacity(1024);
+    let mut entries_by_bucket: Vec<Vec<TxnEntry>> =
+        (0..merkle::NUM_BUCKETS).map(|_| Vec::new()).collect();
+    loop {
+        scanner.scan_entries(&mut batch).map_err(Error::from)?;
+        if batch.len() == 0 {
+            break;
+        }
+        debug!("backup scan entries"; "len" => batch.len());
+        for entry in batch.drain() {
+            if let TxnEntry::Commit {
+                ref default,
+                ref write,
+                ..
+            } = entry
+            {
+                let commit_ts = Key::decode_ts_from(&write.0).unwrap_or(0);
+                // When resuming from a prior tree, entries that were
+                // committed at or before `start_ts` did not change since
+                // that backup and do not need to move the fingerprint or
+                // be re-emitted.
+                if incremental && commit_ts <= start_ts {
+                    continue;
+                }
+                let bucket = new_tree.add_entry(&write.0, commit_ts, &default.1);
+                entries_by_bucket[bucket].push(entry);
+            }
+        }
+    }
+
+    // Descend only into the subtrees whose digests actually differ from
+    // the prior run; on a full backup (no usable prior tree) every bucket
+    // is in scope.
+    let changed: std::collections::HashSet<usize> = if incremental {
+        match new_tree.changed_buckets_since(prior_tree.as_ref().unwrap()) {
+            Some(buckets) => buckets.into_iter().collect(),
+            None => (0..merkle::NUM_BUCKETS).collect(),
+        }
+    } else {
+        (0..merkle::NUM_BUCKETS).collect()
+    };
+
+    if entries_by_bucket.iter().all(Vec::is_empty) {
+        // Nothing was scanned at all, so there are no SST files whose
+        // durability the fingerprint needs to wait on.
+        storage.put(&tree_name, &merkle::encode(&new_tree))?;
+        return Ok((Vec::new(), scanner.take_statistics()));
+    }
+
+    let mut writer = BackupWriter::new(db, &name, limiter.clone())?;
+    for (bucket, entries) in entries_by_bucket.into_iter().enumerate() {
+        if !changed.contains(&bucket) || entries.is_empty() {
+            continue;
         }
+        writer.write(entries.into_iter())?;
     }
+    // Save the SST files durably before advancing the fingerprint: if a
+    // later run saw the fingerprint move without the data behind it, it
+    // would wrongly treat this range's entries as unchanged and skip them.
+    let files = writer.save(storage)?;
+    debug!("backup merkle tree updated";
+        "region_id" => region.get_id(), "root" => new_tree.root());
+    storage.put(&tree_name, &merkle::encode(&new_tree))?;
+    Ok((files, scanner.take_statistics()))
 }
 
 fn key_from_region(region: &Region) -> (Option<Key>, Option<Key>) {
@@ -338,6 +626,7 @@ mod tests {
     use futures::{Future, Stream};
     use kvproto::metapb;
     use std::collections::BTreeMap;
+    use std::fs;
     use std::sync::mpsc::{channel, Receiver, Sender};
     use tempfile::TempDir;
     use tikv::raftstore::coprocessor::{RegionInfo, SeekRegionCallback};
@@ -400,7 +689,13 @@ mod tests {
         let db = rocks.get_rocksdb();
         (
             temp,
-            Endpoint::new(1, rocks, MockRegionInfoProvider::new(), db),
+            Endpoint::new(
+                1,
+                rocks,
+                MockRegionInfoProvider::new(),
+                db,
+                BackupConfig::default(),
+            ),
         )
     }
 
@@ -502,4 +797,93 @@ mod tests {
             tt(start_key, end_key, ranges);
         }
     }
+
+    #[test]
+    fn test_incremental_backup_rerun_emits_changed_key() {
+        let temp = TempDir::new().unwrap();
+        let rocks = TestEngineBuilder::new()
+            .path(temp.path())
+            .cfs(&[engine::CF_DEFAULT, engine::CF_LOCK, engine::CF_WRITE])
+            .build()
+            .unwrap();
+        let db = rocks.get_rocksdb();
+        let storage = TestStorageBuilder::from_engine(rocks.clone()).build().unwrap();
+        let endpoint = Endpoint::new(
+            1,
+            rocks,
+            MockRegionInfoProvider::new(),
+            db,
+            BackupConfig::default(),
+        );
+        endpoint
+            .region_info
+            .set_regions(vec![(b"".to_vec(), b"".to_vec(), 1)]);
+
+        let put = |key: &[u8], value: &[u8], start_ts: u64, commit_ts: u64| {
+            let k = Key::from_raw(key);
+            storage
+                .prewrite(
+                    Context::new(),
+                    vec![Mutation::Put((k.clone(), value.to_vec()))],
+                    key.to_vec(),
+                    start_ts,
+                    Options::default(),
+                )
+                .wait()
+                .unwrap();
+            storage
+                .commit(Context::new(), vec![k], start_ts, commit_ts)
+                .wait()
+                .unwrap();
+        };
+
+        put(b"key1", b"value1", 5, 6);
+
+        let dest = TempDir::new().unwrap();
+        let run = |start_ts: u64, end_ts: u64| -> Vec<BackupResponse> {
+            let ls = LocalStorage::new(dest.path()).unwrap();
+            let (tx, rx) = unbounded();
+            let task = Task {
+                start_key: vec![],
+                end_key: vec![],
+                start_ts,
+                end_ts,
+                resp: tx,
+                storage: Arc::new(ls),
+            };
+            endpoint.handle_backup_task(task);
+            rx.collect().wait().unwrap().into_iter().flatten().collect()
+        };
+
+        // Full backup: the only key so far must show up.
+        let resps = run(6, 6);
+        assert!(resps.iter().any(|r| !r.get_files().is_empty()));
+
+        put(b"key2", b"value2", 10, 11);
+
+        // Incremental re-run against the same destination: the Merkle tree
+        // (and checkpoint manifest, keyed partly by end_ts so it doesn't
+        // collide with the full run's entry above) persisted by the full
+        // run is reused, but the newly committed key must still be
+        // scanned and emitted, not silently dropped via the checkpoint
+        // shortcut or skipped via the Merkle diff.
+        let resps = run(6, 11);
+        let found_key2 = resps.iter().any(|r| {
+            r.get_files().iter().any(|f| {
+                fs::read(dest.path().join(f.get_name()))
+                    .map(|bytes| {
+                        let needle = b"key2";
+                        bytes
+                            .windows(needle.len())
+                            .any(|w| w == needle)
+                    })
+                    .unwrap_or(false)
+            })
+        });
+        assert!(
+            found_key2,
+            "incremental re-run did not emit the newly committed key: {:?}",
+            resps
+        );
+    }
 }
@@ -0,0 +1,127 @@
+use std::error::Error as StdError;
+use std::io::Error as IoError;
+use std::{error, result};
+
+use kvproto::backup::Error as ErrorPb;
+use tikv::storage::kv::Error as EngineError;
+use tikv::storage::txn::Error as TxnError;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(IoError),
+    Engine(EngineError),
+    Txn(TxnError),
+    Other(Box<dyn StdError + Sync + Send>),
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "backup error"
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "Io error {:?}", e),
+            Error::Engine(e) => write!(f, "Engine error {:?}", e),
+            Error::Txn(e) => write!(f, "Txn error {:?}", e),
+            Error::Other(e) => write!(f, "Other error {:?}", e),
+        }
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(err: IoError) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<EngineError> for Error {
+    fn from(err: EngineError) -> Self {
+        Error::Engine(err)
+    }
+}
+
+impl From<TxnError> for Error {
+    fn from(err: TxnError) -> Self {
+        Error::Txn(err)
+    }
+}
+
+impl From<Box<dyn StdError + Sync + Send>> for Error {
+    fn from(err: Box<dyn StdError + Sync + Send>) -> Self {
+        Error::Other(err)
+    }
+}
+
+impl From<Error> for ErrorPb {
+    fn from(err: Error) -> ErrorPb {
+        let mut e = ErrorPb::new();
+        e.set_msg(format!("{}", err));
+        e
+    }
+}
+
+impl Error {
+    /// Whether this failure looks transient (a leader change, a stale
+    /// snapshot, a busy store during a node restart) and is therefore
+    /// worth a bounded automatic retry, as opposed to a permanent failure
+    /// (bad request, corrupt data, disk full) that would just happen
+    /// again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Engine(e) => engine_error_is_retryable(e),
+            Error::Txn(e) => txn_error_is_retryable(e),
+            Error::Io(_) | Error::Other(_) => false,
+        }
+    }
+}
+
+/// Matches the concrete region-error variants that mean "ask again" (the
+/// leader moved, the epoch is stale, the store is busy) rather than
+/// string-matching `Debug` output, which would also match permanent
+/// failures whose message happens to mention one of those words.
+fn engine_error_is_retryable(e: &EngineError) -> bool {
+    match e {
+        EngineError::Request(ref req) => {
+            req.has_not_leader()
+                || req.has_stale_command()
+                || req.has_stale_epoch()
+                || req.has_region_not_found()
+                || req.has_server_is_busy()
+        }
+        EngineError::Timeout(_) => true,
+        _ => false,
+    }
+}
+
+fn txn_error_is_retryable(e: &TxnError) -> bool {
+    match e {
+        TxnError::Engine(ref inner) => engine_error_is_retryable(inner),
+        _ => false,
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_io_and_other_errors_are_not_retryable() {
+        let io_err = Error::from(IoError::new(std::io::ErrorKind::Other, "disk full"));
+        assert!(!io_err.is_retryable());
+
+        let other_err = Error::Other("bad config".into());
+        assert!(!other_err.is_retryable());
+    }
+
+    #[test]
+    fn test_engine_timeout_is_retryable() {
+        let err = Error::Engine(EngineError::Timeout(Duration::from_secs(1)));
+        assert!(err.is_retryable());
+    }
+}
@@ -0,0 +1,19 @@
+#[macro_use]
+extern crate tikv_util;
+
+mod checkpoint;
+mod config;
+mod endpoint;
+mod errors;
+mod limiter;
+mod merkle;
+mod s3;
+mod storage;
+mod writer;
+
+pub use config::BackupConfig;
+pub use endpoint::{BackupRange, Endpoint, Task};
+pub use errors::{Error, Result};
+pub use limiter::Limiter;
+pub use storage::{create_storage, LocalStorage, Storage};
+pub use writer::BackupWriter;
@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A shared token-bucket rate limiter. Every `BackupWriter` draws from the
+/// same `Limiter` before flushing SST bytes, so the aggregate backup
+/// throughput stays under the configured cap no matter how many regions
+/// are being backed up in parallel.
+#[derive(Clone)]
+pub struct Limiter {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    bytes_per_sec: AtomicU64,
+    available: AtomicI64,
+    last_refill: Mutex<Instant>,
+}
+
+impl Limiter {
+    /// `bytes_per_sec == 0` means unlimited.
+    pub fn new(bytes_per_sec: u64) -> Limiter {
+        Limiter {
+            inner: Arc::new(Inner {
+                bytes_per_sec: AtomicU64::new(bytes_per_sec),
+                available: AtomicI64::new(bytes_per_sec as i64),
+                last_refill: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+
+    /// Adjust the speed limit at runtime without restarting the backup
+    /// worker. Resets the bucket so the change takes effect immediately:
+    /// without this, enabling a limit after running unlimited (or after an
+    /// idle period) would see a huge elapsed interval on the next
+    /// `consume` and refill an effectively unlimited burst.
+    pub fn set_speed_limit(&self, bytes_per_sec: u64) {
+        self.inner.bytes_per_sec.store(bytes_per_sec, Ordering::SeqCst);
+        self.inner.available.store(bytes_per_sec as i64, Ordering::SeqCst);
+        *self.inner.last_refill.lock().unwrap() = Instant::now();
+    }
+
+    pub fn speed_limit(&self) -> u64 {
+        self.inner.bytes_per_sec.load(Ordering::SeqCst)
+    }
+
+    /// Block the calling thread until `bytes` worth of budget is
+    /// available, refilling the bucket based on elapsed wall-clock time.
+    pub fn consume(&self, bytes: usize) {
+        let limit = self.speed_limit();
+        if limit == 0 {
+            return;
+        }
+        // Draw the full amount from the bucket exactly once, even if that
+        // drives `available` negative (a single request bigger than one
+        // second's budget, e.g. a whole CF's SST file). The loop below
+        // only *waits* for refills to bring the balance back to
+        // non-negative; it must never subtract `bytes` again on a
+        // wakeup, or a request bigger than `limit` could never be
+        // satisfied once every refill step is itself capped at `limit`.
+        self.inner.available.fetch_sub(bytes as i64, Ordering::SeqCst);
+        loop {
+            let available = {
+                let mut last_refill = self.inner.last_refill.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(*last_refill);
+                // Cap the elapsed interval itself at one second's worth so
+                // a single refill step can never grant more than `limit`
+                // bytes of credit. Without this, the time spent waiting
+                // out a large request (or an idle gap) would be credited
+                // in full once added to an already-deep deficit, silently
+                // bypassing the post-add cap below.
+                let capped_elapsed = elapsed.min(Duration::from_secs(1));
+                let refill = (capped_elapsed.as_secs_f64() * limit as f64) as i64;
+                if refill > 0 {
+                    let refilled = self.inner.available.fetch_add(refill, Ordering::SeqCst) + refill;
+                    // Also clamp the standing balance at one second's
+                    // worth so idle time can't keep compounding burst
+                    // credit across separate calls.
+                    if refilled > limit as i64 {
+                        self.inner
+                            .available
+                            .fetch_sub(refilled - limit as i64, Ordering::SeqCst);
+                    }
+                    *last_refill = now;
+                }
+                self.inner.available.load(Ordering::SeqCst)
+            };
+            if available >= 0 {
+                return;
+            }
+            let wait = Duration::from_secs_f64((-available) as f64 / limit as f64);
+            thread::sleep(wait.min(Duration::from_secs(1)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consume_within_budget_does_not_block() {
+        let limiter = Limiter::new(1_000_000);
+        let start = Instant::now();
+        limiter.consume(1_000);
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_consume_unlimited_never_blocks() {
+        let limiter = Limiter::new(0);
+        let start = Instant::now();
+        limiter.consume(10_000_000);
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_set_speed_limit_resets_burst_instead_of_allowing_one() {
+        // Run unlimited for a while, accruing whatever "elapsed time" would
+        // otherwise translate into burst credit, then cap the rate. The
+        // very next consume over the new budget must still be paced, not
+        // let through by a stale `available`/`last_refill`.
+        let limiter = Limiter::new(0);
+        limiter.consume(10_000_000);
+        limiter.set_speed_limit(100_000);
+
+        let start = Instant::now();
+        limiter.consume(150_000);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(400),
+            "expected consume to be paced after set_speed_limit, elapsed {:?}",
+            elapsed
+        );
+    }
+
+    #[test]
+    fn test_consume_larger_than_limit_does_not_hang() {
+        // A single SST file routinely exceeds a per-second rate cap; this
+        // must still complete (by waiting across multiple refills) rather
+        // than looping forever because no single refill ever reaches
+        // `bytes`.
+        let limiter = Limiter::new(100_000);
+        let start = Instant::now();
+        limiter.consume(250_000);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(1_400) && elapsed < Duration::from_secs(5),
+            "expected ~1.5s of pacing for a 250_000 byte request at 100_000/s, got {:?}",
+            elapsed
+        );
+    }
+
+    #[test]
+    fn test_idle_period_does_not_bank_unbounded_burst() {
+        let limiter = Limiter::new(100_000);
+        // Drain the initial bucket.
+        limiter.consume(100_000);
+        // Idle long enough that an uncapped bucket would refill well past
+        // one second's worth of budget (150_000 bytes at 100_000/sec).
+        thread::sleep(Duration::from_millis(1_500));
+
+        let start = Instant::now();
+        limiter.consume(150_000);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(300),
+            "expected the bucket to stay capped at ~1s of budget instead of \
+             banking the whole idle period as burst, elapsed {:?}",
+            elapsed
+        );
+    }
+}
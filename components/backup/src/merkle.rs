@@ -0,0 +1,215 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Number of fixed-size key buckets each region's Merkle fingerprint tree
+/// is split into. Kept constant so trees built on independent backup runs
+/// over the same region stay directly comparable.
+pub const NUM_BUCKETS: usize = 16;
+
+pub type Digest = u64;
+
+/// A bottom-up hash tree over one region's key space. Used to work out,
+/// without re-reading unchanged data, which key subranges actually
+/// changed between two backups of the same region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleTree {
+    /// Region epoch version the tree was built against. A mismatch with
+    /// the region's current epoch means the region split/merged since
+    /// and the tree must be discarded.
+    pub epoch_version: u64,
+    /// The `[start, end)` of the scanned key range, as `(start, end)`
+    /// (`end` empty means "no upper bound"). Recorded so a tree built for
+    /// a different range is never mistaken for comparable.
+    pub bounds: Vec<Vec<u8>>,
+    /// Per-bucket leaf digests, in bucket order.
+    pub leaves: Vec<Digest>,
+}
+
+impl MerkleTree {
+    pub fn empty(epoch_version: u64, start: &[u8], end: Option<&[u8]>) -> MerkleTree {
+        MerkleTree {
+            epoch_version,
+            bounds: vec![start.to_vec(), end.map(|e| e.to_vec()).unwrap_or_default()],
+            leaves: vec![0; NUM_BUCKETS],
+        }
+    }
+
+    /// The digest of the whole tree, computed bottom-up from the leaves.
+    pub fn root(&self) -> Digest {
+        let mut level = self.leaves.clone();
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity((level.len() + 1) / 2);
+            for pair in level.chunks(2) {
+                let mut hasher = DefaultHasher::new();
+                pair[0].hash(&mut hasher);
+                pair.get(1).unwrap_or(&pair[0]).hash(&mut hasher);
+                next.push(hasher.finish());
+            }
+            level = next;
+        }
+        level.first().copied().unwrap_or_default()
+    }
+
+    /// Fold one scanned entry into the leaf of the bucket that `key`
+    /// belongs to, returning the bucket index it landed in.
+    pub fn add_entry(&mut self, key: &[u8], commit_ts: u64, value: &[u8]) -> usize {
+        let bucket = bucket_of(key);
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        commit_ts.hash(&mut hasher);
+        value.hash(&mut hasher);
+        self.leaves[bucket] ^= hasher.finish();
+        bucket
+    }
+
+    /// Compare against a previously persisted tree for the same region,
+    /// returning the indices of the buckets whose content changed.
+    /// Subtrees whose digests already match are skipped entirely.
+    /// Returns `None` (meaning: fall back to a full scan) when the prior
+    /// tree was built against a different region epoch, key range or
+    /// bucket layout.
+    pub fn changed_buckets_since(&self, prior: &MerkleTree) -> Option<Vec<usize>> {
+        if prior.epoch_version != self.epoch_version
+            || prior.bounds != self.bounds
+            || prior.leaves.len() != self.leaves.len()
+        {
+            return None;
+        }
+        Some(diff_leaves(&prior.leaves, &self.leaves))
+    }
+}
+
+fn diff_leaves(old: &[Digest], new: &[Digest]) -> Vec<usize> {
+    fn walk(old: &[Digest], new: &[Digest], start: usize, out: &mut Vec<usize>) {
+        if old == new {
+            return;
+        }
+        if old.len() == 1 {
+            out.push(start);
+            return;
+        }
+        let mid = old.len() / 2;
+        walk(&old[..mid], &new[..mid], start, out);
+        walk(&old[mid..], &new[mid..], start + mid, out);
+    }
+    let mut out = Vec::new();
+    walk(old, new, 0, &mut out);
+    out
+}
+
+/// Assign `key` to one of `NUM_BUCKETS` buckets by hashing the whole key,
+/// a pure, deterministic function of the key so trees from different runs
+/// stay comparable. TiKV's encoded keys share long common prefixes (`z` +
+/// table id + ...), so bucketing on a fixed-width key prefix (as an
+/// earlier version of this function did) collapses every entry in a
+/// table into the same bucket; hashing the full key spreads entries
+/// evenly regardless of shared prefix length.
+fn bucket_of(key: &[u8]) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % NUM_BUCKETS as u64) as usize
+}
+
+/// Serialize a tree for persistence in `Storage`.
+pub fn encode(tree: &MerkleTree) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&tree.epoch_version.to_le_bytes());
+    buf.extend_from_slice(&(tree.bounds.len() as u64).to_le_bytes());
+    for b in &tree.bounds {
+        buf.extend_from_slice(&(b.len() as u64).to_le_bytes());
+        buf.extend_from_slice(b);
+    }
+    buf.extend_from_slice(&(tree.leaves.len() as u64).to_le_bytes());
+    for l in &tree.leaves {
+        buf.extend_from_slice(&l.to_le_bytes());
+    }
+    buf
+}
+
+/// Deserialize a tree persisted by `encode`.
+pub fn decode(mut data: &[u8]) -> Option<MerkleTree> {
+    let epoch_version = take_u64(&mut data)?;
+    let bounds_len = take_u64(&mut data)? as usize;
+    let mut bounds = Vec::with_capacity(bounds_len);
+    for _ in 0..bounds_len {
+        let len = take_u64(&mut data)? as usize;
+        if data.len() < len {
+            return None;
+        }
+        bounds.push(data[..len].to_vec());
+        data = &data[len..];
+    }
+    let leaves_len = take_u64(&mut data)? as usize;
+    let mut leaves = Vec::with_capacity(leaves_len);
+    for _ in 0..leaves_len {
+        leaves.push(take_u64(&mut data)?);
+    }
+    Some(MerkleTree {
+        epoch_version,
+        bounds,
+        leaves,
+    })
+}
+
+fn take_u64(data: &mut &[u8]) -> Option<u64> {
+    if data.len() < 8 {
+        return None;
+    }
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&data[..8]);
+    *data = &data[8..];
+    Some(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let mut tree = MerkleTree::empty(3, b"a", Some(&b"z"[..]));
+        tree.add_entry(b"abc", 5, b"value");
+        tree.add_entry(b"xyz", 7, b"other");
+        let decoded = decode(&encode(&tree)).unwrap();
+        assert_eq!(tree, decoded);
+    }
+
+    #[test]
+    fn test_bucket_of_spreads_common_prefix_keys() {
+        // Keys sharing a long common prefix (as TiKV's encoded keys do)
+        // must not all collapse into the same bucket.
+        let keys: Vec<Vec<u8>> = (0..64)
+            .map(|i| {
+                let mut k = b"zt_100_r".to_vec();
+                k.extend_from_slice(&(i as u64).to_be_bytes());
+                k
+            })
+            .collect();
+        let buckets: std::collections::HashSet<usize> =
+            keys.iter().map(|k| bucket_of(k)).collect();
+        assert!(
+            buckets.len() > 1,
+            "expected keys with a shared prefix to land in more than one bucket"
+        );
+    }
+
+    #[test]
+    fn test_changed_buckets_since_detects_diff() {
+        let mut prior = MerkleTree::empty(1, b"a", None);
+        prior.add_entry(b"abc", 1, b"v1");
+
+        let mut current = prior.clone();
+        current.add_entry(b"abc", 2, b"v2");
+
+        let changed = current.changed_buckets_since(&prior).unwrap();
+        assert!(!changed.is_empty());
+        assert_eq!(current.changed_buckets_since(&current), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_changed_buckets_since_epoch_mismatch_falls_back() {
+        let prior = MerkleTree::empty(1, b"a", None);
+        let current = MerkleTree::empty(2, b"a", None);
+        assert_eq!(current.changed_buckets_since(&prior), None);
+    }
+}
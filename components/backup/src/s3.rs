@@ -0,0 +1,351 @@
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+use rusoto_core::{Region, RusotoError};
+use rusoto_s3::{
+    AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
+    CompletedPart, CreateMultipartUploadRequest, GetObjectError, GetObjectRequest,
+    PutObjectRequest, S3Client, UploadPartRequest, S3,
+};
+use url::Url;
+
+use crate::storage::Storage;
+
+/// Parts are uploaded to S3 one at a time as they're read off the caller's
+/// reader, so a region's backup never has to be buffered fully in memory
+/// before being shipped out: at most one part is resident at once. Below
+/// this size (read in a single, undersized first part) a plain
+/// single-shot `PutObject` is used instead of multipart.
+const MULTIPART_PART_SIZE: usize = 8 * 1024 * 1024;
+const MAX_RETRIES: u32 = 5;
+
+/// An S3-compatible `Storage` backend, parsed from an `s3://bucket/prefix`
+/// destination path. Also works against MinIO and other S3-compatible
+/// gateways by overriding the endpoint.
+pub struct S3Storage {
+    client: S3Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Storage {
+    /// Parse an `s3://bucket/prefix` URL. The region and a custom endpoint
+    /// (for MinIO / self-hosted gateways) may be supplied as query
+    /// parameters, e.g.
+    /// `s3://bucket/prefix?region=us-east-1&endpoint=http://127.0.0.1:9000`.
+    /// Other query parameters are ignored; credentials are not read from
+    /// the URL, and the client uses rusoto's default credential chain.
+    pub fn from_url(path: &str) -> crate::Result<S3Storage> {
+        let url = Url::parse(path)
+            .map_err(|e| crate::Error::Other(Box::new(e)))?;
+        let bucket = url
+            .host_str()
+            .ok_or_else(|| {
+                crate::Error::Other(
+                    format!("s3 path {} is missing a bucket", path).into(),
+                )
+            })?
+            .to_owned();
+        let prefix = url.path().trim_start_matches('/').to_owned();
+
+        let mut region_name = None;
+        let mut endpoint = None;
+        for (k, v) in url.query_pairs() {
+            match &*k {
+                "region" => region_name = Some(v.into_owned()),
+                "endpoint" => endpoint = Some(v.into_owned()),
+                _ => {}
+            }
+        }
+        let region = match endpoint {
+            Some(endpoint) => Region::Custom {
+                name: region_name.unwrap_or_else(|| "custom".to_owned()),
+                endpoint,
+            },
+            None => region_name
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(Region::UsEast1),
+        };
+
+        let client = S3Client::new(region);
+        Ok(S3Storage {
+            client,
+            bucket,
+            prefix,
+        })
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}/{}", self.prefix, name)
+        }
+    }
+
+    fn put_small(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        retry_with_backoff(MAX_RETRIES, || {
+            let req = PutObjectRequest {
+                bucket: self.bucket.clone(),
+                key: key.to_owned(),
+                body: Some(data.to_vec().into()),
+                ..Default::default()
+            };
+            self.client
+                .put_object(req)
+                .sync()
+                .map(|_| ())
+                .map_err(rusoto_to_io_error)
+        })
+    }
+
+    /// Upload `reader`'s content as a multipart object, starting from the
+    /// already-read `first_part`. Only one part (`MULTIPART_PART_SIZE`
+    /// bytes) is ever held in memory at a time, instead of the whole
+    /// object.
+    fn put_multipart(&self, key: &str, first_part: Vec<u8>, reader: &mut dyn Read) -> io::Result<()> {
+        let create = CreateMultipartUploadRequest {
+            bucket: self.bucket.clone(),
+            key: key.to_owned(),
+            ..Default::default()
+        };
+        let upload_id = retry_with_backoff(MAX_RETRIES, || {
+            self.client
+                .create_multipart_upload(create.clone())
+                .sync()
+                .map_err(rusoto_to_io_error)
+        })?
+        .upload_id
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "missing upload id"))?;
+
+        let mut parts = Vec::new();
+        let mut part_number = 1i64;
+        let mut chunk = first_part;
+        let upload_result = loop {
+            if chunk.is_empty() {
+                break Ok(());
+            }
+            let full_part = chunk.len() == MULTIPART_PART_SIZE;
+            let res = retry_with_backoff(MAX_RETRIES, || {
+                let req = UploadPartRequest {
+                    bucket: self.bucket.clone(),
+                    key: key.to_owned(),
+                    upload_id: upload_id.clone(),
+                    part_number,
+                    body: Some(chunk.clone().into()),
+                    ..Default::default()
+                };
+                self.client.upload_part(req).sync().map_err(rusoto_to_io_error)
+            });
+            match res {
+                Ok(part) => parts.push(CompletedPart {
+                    e_tag: part.e_tag,
+                    part_number: Some(part_number),
+                }),
+                Err(e) => break Err(e),
+            }
+            // A part shorter than the requested size means the reader is
+            // exhausted; a full-size part means there may be more to come.
+            if !full_part {
+                break Ok(());
+            }
+            part_number += 1;
+            chunk = match read_part(reader, MULTIPART_PART_SIZE) {
+                Ok(next) => next,
+                Err(e) => break Err(e),
+            };
+        };
+
+        if let Err(e) = upload_result {
+            let _ = self.client.abort_multipart_upload(AbortMultipartUploadRequest {
+                bucket: self.bucket.clone(),
+                key: key.to_owned(),
+                upload_id: upload_id.clone(),
+                ..Default::default()
+            });
+            return Err(e);
+        }
+
+        retry_with_backoff(MAX_RETRIES, || {
+            let req = CompleteMultipartUploadRequest {
+                bucket: self.bucket.clone(),
+                key: key.to_owned(),
+                upload_id: upload_id.clone(),
+                multipart_upload: Some(CompletedMultipartUpload {
+                    parts: Some(parts.clone()),
+                }),
+                ..Default::default()
+            };
+            self.client
+                .complete_multipart_upload(req)
+                .sync()
+                .map(|_| ())
+                .map_err(rusoto_to_io_error)
+        })
+    }
+}
+
+impl Storage for S3Storage {
+    fn save_file(&self, name: &str, reader: &mut dyn Read) -> io::Result<()> {
+        let key = self.key(name);
+        // Read one part's worth up front: if the reader is already
+        // exhausted within a single part, a plain `PutObject` avoids the
+        // extra create/complete round trips of multipart.
+        let first_part = read_part(reader, MULTIPART_PART_SIZE)?;
+        if first_part.len() < MULTIPART_PART_SIZE {
+            self.put_small(&key, &first_part)
+        } else {
+            self.put_multipart(&key, first_part, reader)
+        }
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.put_small(&self.key(name), data)
+    }
+
+    fn get(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        let key = self.key(name);
+        let res = retry_with_backoff(MAX_RETRIES, || {
+            self.client
+                .get_object(GetObjectRequest {
+                    bucket: self.bucket.clone(),
+                    key: key.clone(),
+                    ..Default::default()
+                })
+                .sync()
+                .map_err(get_object_error_to_io_error)
+        });
+        match res {
+            Ok(obj) => {
+                let mut buf = Vec::new();
+                if let Some(body) = obj.body {
+                    body.into_blocking_read().read_to_end(&mut buf)?;
+                }
+                Ok(Some(buf))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn full_key(&self, name: &str) -> String {
+        self.key(name)
+    }
+}
+
+/// Read up to `size` bytes from `reader`, stopping early at EOF. The
+/// returned buffer is shorter than `size` only when the reader is
+/// exhausted, which callers use as the multipart "last part" signal.
+fn read_part(reader: &mut dyn Read, size: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; size];
+    let mut filled = 0;
+    while filled < size {
+        let n = reader.read(&mut buf[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    buf.truncate(filled);
+    Ok(buf)
+}
+
+/// Retry a fallible S3 call with exponential backoff. Only transient
+/// errors are worth retrying; permanent failures (bad request, missing
+/// bucket, ...) are still surfaced immediately through the normal
+/// `response.set_error` path on the caller's side.
+fn retry_with_backoff<T>(max_retries: u32, mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt < max_retries && is_transient(&e) => {
+                let backoff = Duration::from_millis(100 * 2u64.pow(attempt));
+                thread::sleep(backoff);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn is_transient(e: &io::Error) -> bool {
+    !matches!(
+        e.kind(),
+        io::ErrorKind::NotFound | io::ErrorKind::InvalidInput | io::ErrorKind::PermissionDenied
+    )
+}
+
+/// Maps a rusoto error to an `io::Error`, classifying the permanent failure
+/// modes (bad/missing credentials, a 403 from the bucket policy) as
+/// `PermissionDenied` rather than `Other`. Without this, `is_transient`
+/// treats every non-404 error as retryable, so a misconfigured credential
+/// burns all `MAX_RETRIES` attempts with backoff on every single call
+/// instead of failing fast.
+fn rusoto_to_io_error<E: std::fmt::Display>(e: RusotoError<E>) -> io::Error {
+    match e {
+        RusotoError::Credentials(ref _err) => {
+            io::Error::new(io::ErrorKind::PermissionDenied, format!("{}", e))
+        }
+        RusotoError::Unknown(ref resp) if resp.status.as_u16() == 404 => {
+            io::Error::new(io::ErrorKind::NotFound, format!("{}", e))
+        }
+        RusotoError::Unknown(ref resp) if resp.status.as_u16() == 403 => {
+            io::Error::new(io::ErrorKind::PermissionDenied, format!("{}", e))
+        }
+        _ => io::Error::new(io::ErrorKind::Other, format!("{}", e)),
+    }
+}
+
+/// Like `rusoto_to_io_error`, but also recognizes `GetObjectError::NoSuchKey`
+/// as a missing object. A bare `Service(NoSuchKey)` never carries an HTTP
+/// status, so `rusoto_to_io_error`'s generic 404 check alone never fires
+/// for it: without this, a first-ever backup's manifest/Merkle-tree lookup
+/// (both always absent on the first run) would be mapped to
+/// `ErrorKind::Other` and retried `MAX_RETRIES` times with backoff before
+/// `get` finally reports the object missing.
+fn get_object_error_to_io_error(e: RusotoError<GetObjectError>) -> io::Error {
+    if let RusotoError::Service(GetObjectError::NoSuchKey(ref msg)) = e {
+        return io::Error::new(io::ErrorKind::NotFound, msg.clone());
+    }
+    rusoto_to_io_error(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_part_splits_on_size() {
+        let data = vec![7u8; 10];
+        let mut reader = Cursor::new(data.clone());
+        let first = read_part(&mut reader, 4).unwrap();
+        assert_eq!(first, vec![7u8; 4]);
+        let second = read_part(&mut reader, 4).unwrap();
+        assert_eq!(second, vec![7u8; 4]);
+        // Short read (len < size) signals EOF to the caller.
+        let third = read_part(&mut reader, 4).unwrap();
+        assert_eq!(third, vec![7u8; 2]);
+        let fourth = read_part(&mut reader, 4).unwrap();
+        assert!(fourth.is_empty());
+    }
+
+    #[test]
+    fn test_get_object_error_maps_no_such_key_to_not_found() {
+        let e = get_object_error_to_io_error(RusotoError::Service(GetObjectError::NoSuchKey(
+            "not there".to_owned(),
+        )));
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_permission_denied_is_not_transient() {
+        let e = rusoto_to_io_error(RusotoError::<GetObjectError>::Credentials(
+            rusoto_core::CredentialsError::new("no credentials in environment"),
+        ));
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        assert!(!is_transient(&e), "a bad-credentials error must not be retried");
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::Result;
+
+/// An abstraction over the destination a backup writes its SST files (and
+/// auxiliary metadata, such as Merkle fingerprints) to.
+pub trait Storage: Sync + Send {
+    /// Persist a file's content read from `reader` under `name`. Takes a
+    /// reader rather than a buffer so a backend that supports it (e.g. S3
+    /// multipart upload) can stream the file part by part instead of
+    /// holding the whole thing in memory at once.
+    fn save_file(&self, name: &str, reader: &mut dyn io::Read) -> io::Result<()>;
+
+    /// Persist an auxiliary blob (not an SST file) under `name`, e.g. a
+    /// Merkle tree snapshot or a checkpoint manifest.
+    fn put(&self, name: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Load a previously persisted auxiliary blob, if any.
+    fn get(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// The fully-qualified location `name` was (or will be) saved under,
+    /// e.g. an object key including bucket prefix. Restore uses this to
+    /// locate the file, so it is recorded in the `File` messages returned
+    /// from a backup. Local storage has no prefixing, so `name` itself is
+    /// the fully-qualified location.
+    fn full_key(&self, name: &str) -> String {
+        name.to_owned()
+    }
+}
+
+/// Stores backup files in a directory on the local filesystem.
+pub struct LocalStorage {
+    base: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(base: &Path) -> io::Result<LocalStorage> {
+        fs::create_dir_all(base)?;
+        Ok(LocalStorage {
+            base: base.to_owned(),
+        })
+    }
+}
+
+impl Storage for LocalStorage {
+    fn save_file(&self, name: &str, reader: &mut dyn io::Read) -> io::Result<()> {
+        let mut f = fs::File::create(self.base.join(name))?;
+        io::copy(reader, &mut f)?;
+        f.sync_data()
+    }
+
+    fn put(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        self.save_file(name, &mut io::Cursor::new(data))
+    }
+
+    fn get(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.base.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Creates a `Storage` from a backup destination path. A bare path (or a
+/// `local://` URL) is backed by the local filesystem; an `s3://` URL is
+/// backed by an S3-compatible object store (see `crate::s3`).
+pub fn create_storage(path: &str) -> Result<std::sync::Arc<dyn Storage>> {
+    if path.starts_with("s3://") {
+        let storage = crate::s3::S3Storage::from_url(path)?;
+        return Ok(std::sync::Arc::new(storage));
+    }
+    let stripped = path.trim_start_matches("local://");
+    let storage = LocalStorage::new(Path::new(stripped))?;
+    Ok(std::sync::Arc::new(storage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_storage_save_file_streams_reader() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp.path()).unwrap();
+        let data = b"some sst bytes".to_vec();
+        storage
+            .save_file("region.sst", &mut io::Cursor::new(&data))
+            .unwrap();
+        assert_eq!(fs::read(temp.path().join("region.sst")).unwrap(), data);
+    }
+
+    #[test]
+    fn test_local_storage_get_missing_returns_none() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let storage = LocalStorage::new(temp.path()).unwrap();
+        assert_eq!(storage.get("missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use engine::rocks::{SstFileWriter, SstWriterBuilder};
+use engine::DB;
+use kvproto::backup::File;
+use tikv::storage::txn::TxnEntry;
+
+use crate::limiter::Limiter;
+use crate::storage::Storage;
+use crate::{Error, Result};
+
+/// Buffers scanned MVCC entries into per-CF SST files on local disk and
+/// streams the finished files to a `Storage` once a backup range has been
+/// fully scanned, without ever holding a whole SST's content in memory at
+/// once. Draws from a shared `Limiter` before every flush so the aggregate
+/// backup throughput across all in-flight regions stays under the
+/// configured cap.
+pub struct BackupWriter {
+    name: String,
+    default_path: PathBuf,
+    write_path: PathBuf,
+    default: SstFileWriter,
+    write: SstFileWriter,
+    default_written: bool,
+    write_written: bool,
+    limiter: Limiter,
+}
+
+impl BackupWriter {
+    pub fn new(db: Arc<DB>, name: &str, limiter: Limiter) -> Result<BackupWriter> {
+        let dir = std::env::temp_dir().join("tikv-backup-sst");
+        fs::create_dir_all(&dir).map_err(Error::from)?;
+        let default_path = dir.join(format!("{}_default.sst", name));
+        let write_path = dir.join(format!("{}_write.sst", name));
+
+        let default = SstWriterBuilder::new()
+            .set_db(db.clone())
+            .set_cf(engine::CF_DEFAULT)
+            .build(default_path.to_str().unwrap())
+            .map_err(|e| Error::Other(e.into()))?;
+        let write = SstWriterBuilder::new()
+            .set_db(db)
+            .set_cf(engine::CF_WRITE)
+            .build(write_path.to_str().unwrap())
+            .map_err(|e| Error::Other(e.into()))?;
+        Ok(BackupWriter {
+            name: name.to_owned(),
+            default_path,
+            write_path,
+            default,
+            write,
+            default_written: false,
+            write_written: false,
+            limiter,
+        })
+    }
+
+    /// Write a batch of scanned entries into the appropriate per-CF SST.
+    pub fn write<I>(&mut self, entries: I) -> Result<()>
+    where
+        I: Iterator<Item = TxnEntry>,
+    {
+        for entry in entries {
+            if let TxnEntry::Commit { default, write, .. } = entry {
+                if !default.0.is_empty() {
+                    self.limiter.consume(default.1.len());
+                    self.default
+                        .put(&default.0, &default.1)
+                        .map_err(|e| Error::Other(e.into()))?;
+                    self.default_written = true;
+                }
+                self.limiter.consume(write.1.len());
+                self.write
+                    .put(&write.0, &write.1)
+                    .map_err(|e| Error::Other(e.into()))?;
+                self.write_written = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish the SST files and save them to `storage`, returning the
+    /// resulting `File` descriptors.
+    pub fn save(mut self, storage: &Arc<dyn Storage>) -> Result<Vec<File>> {
+        let mut files = Vec::with_capacity(2);
+        if self.write_written {
+            files.push(Self::save_cf(
+                &self.name,
+                "write",
+                &mut self.write,
+                &self.write_path,
+                storage,
+                &self.limiter,
+            )?);
+        }
+        if self.default_written {
+            files.push(Self::save_cf(
+                &self.name,
+                "default",
+                &mut self.default,
+                &self.default_path,
+                storage,
+                &self.limiter,
+            )?);
+        }
+        Ok(files)
+    }
+
+    /// Finalize a single CF's SST (already fully written to `path` on
+    /// local disk by `SstFileWriter`) and stream it into `storage` by
+    /// reading straight off disk, rather than materializing the whole
+    /// file's bytes in memory first.
+    fn save_cf(
+        name: &str,
+        cf: &str,
+        writer: &mut SstFileWriter,
+        path: &std::path::Path,
+        storage: &Arc<dyn Storage>,
+        limiter: &Limiter,
+    ) -> Result<File> {
+        let info = writer.finish().map_err(|e| Error::Other(e.into()))?;
+        let size = info.file_size();
+        limiter.consume(size as usize);
+        let file_name = format!("{}_{}.sst", name, cf);
+        let mut local_file = fs::File::open(path).map_err(Error::from)?;
+        storage.save_file(&file_name, &mut local_file)?;
+        // The local copy only exists to stream from; it is safe to drop
+        // once `storage` has durably saved its own copy.
+        let _ = fs::remove_file(path);
+        let mut file = File::new();
+        // Record the fully-qualified location (e.g. the S3 object key) so
+        // restore can find the file regardless of backend.
+        file.set_name(storage.full_key(&file_name));
+        file.set_cf(cf.to_owned());
+        file.set_size(size);
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+    use tikv::storage::TestEngineBuilder;
+
+    #[test]
+    fn test_save_with_nothing_written_produces_no_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let rocks = TestEngineBuilder::new()
+            .path(temp.path())
+            .cfs(&[engine::CF_DEFAULT, engine::CF_LOCK, engine::CF_WRITE])
+            .build()
+            .unwrap();
+        let db = rocks.get_rocksdb();
+
+        let writer = BackupWriter::new(db, "1_2_3", Limiter::new(0)).unwrap();
+        let dest = tempfile::TempDir::new().unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(dest.path()).unwrap());
+        let files = writer.save(&storage).unwrap();
+        assert!(files.is_empty());
+    }
+}